@@ -0,0 +1,74 @@
+use std::{fs::File, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::{spawn_blocking, JoinHandle},
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{guard::validate_relative_path, manifest::PreviewPayload, policy::OnErrorPolicy};
+
+/// A single asset queued for inclusion in the output zip archive.
+pub struct ZipEntry {
+    pub asset_path: String,
+    pub asset_bytes: Vec<u8>,
+    pub asset_meta_bytes: Option<Vec<u8>>,
+    pub preview: Option<PreviewPayload>,
+}
+
+/// Spawns the dedicated task that owns the zip writer and serializes every queued asset into
+/// it, since `zip::ZipWriter` is synchronous and cannot be written to concurrently. Returns a
+/// sender to queue entries on and a handle to await once the sender side is dropped.
+pub fn spawn_zip_writer(
+    output_path: PathBuf,
+    on_error: OnErrorPolicy,
+) -> (UnboundedSender<ZipEntry>, JoinHandle<Result<()>>) {
+    let (tx, mut rx) = unbounded_channel::<ZipEntry>();
+
+    let handle = spawn_blocking(move || -> Result<()> {
+        let file = File::create(&output_path)
+            .with_context(|| format!("failed to create output zip {}", output_path.display()))?;
+        let mut writer = ZipWriter::new(file);
+        let options = SimpleFileOptions::default();
+
+        while let Some(entry) = rx.blocking_recv() {
+            if let Err(error) = write_zip_entry(&mut writer, options, &entry) {
+                if !on_error.handle(&entry.asset_path, &error) {
+                    return Err(error);
+                }
+            }
+        }
+
+        writer.finish().context("failed to finalize output zip")?;
+        Ok(())
+    });
+
+    (tx, handle)
+}
+
+fn write_zip_entry(
+    writer: &mut ZipWriter<File>,
+    options: SimpleFileOptions,
+    entry: &ZipEntry,
+) -> Result<()> {
+    let asset_path = validate_relative_path(&entry.asset_path)?;
+    writer.start_file(asset_path.to_string_lossy(), options)?;
+    writer.write_all(&entry.asset_bytes)?;
+
+    if let Some(meta_bytes) = entry.asset_meta_bytes.as_ref() {
+        let meta_name = format!("{}.meta", entry.asset_path);
+        validate_relative_path(&meta_name)?;
+        writer.start_file(meta_name, options)?;
+        writer.write_all(meta_bytes)?;
+    }
+
+    if let Some(preview) = entry.preview.as_ref() {
+        let preview_path = preview.relative_path(&entry.asset_path);
+        validate_relative_path(&preview_path)?;
+        writer.start_file(preview_path, options)?;
+        writer.write_all(&preview.bytes)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use glob::Pattern;
+
+/// Default disposition applied to an asset when no `--include` pattern is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DefaultInclude {
+    /// Match every asset unless it hits an `--exclude` pattern.
+    All,
+    /// Match no asset unless it hits an `--include` pattern.
+    None,
+}
+
+/// Compiled `--include`/`--exclude` glob pattern set, matched against a decoded `pathname`.
+#[derive(Debug, Clone)]
+pub struct AssetFilter {
+    includes: Vec<Pattern>,
+    excludes: Vec<Pattern>,
+    default_include: DefaultInclude,
+}
+
+impl AssetFilter {
+    /// Compiles the raw `--include`/`--exclude` pattern strings once up front.
+    pub fn compile(
+        includes: &[String],
+        excludes: &[String],
+        default_include: DefaultInclude,
+    ) -> Result<AssetFilter> {
+        let includes = includes
+            .iter()
+            .map(|pattern| Pattern::new(pattern).with_context(|| format!("invalid --include pattern: {pattern}")))
+            .collect::<Result<_>>()?;
+        let excludes = excludes
+            .iter()
+            .map(|pattern| Pattern::new(pattern).with_context(|| format!("invalid --exclude pattern: {pattern}")))
+            .collect::<Result<_>>()?;
+
+        Ok(AssetFilter {
+            includes,
+            excludes,
+            default_include,
+        })
+    }
+
+    /// Returns whether `pathname` should be extracted: excludes always win; otherwise, when
+    /// include patterns are given, only an include match passes; with no include patterns the
+    /// configured default applies.
+    pub fn is_match(&self, pathname: &str) -> bool {
+        if self.excludes.iter().any(|pattern| pattern.matches(pathname)) {
+            return false;
+        }
+
+        if self.includes.is_empty() {
+            return self.default_include == DefaultInclude::All;
+        }
+
+        self.includes.iter().any(|pattern| pattern.matches(pathname))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_all_matches_everything_without_includes() {
+        let filter = AssetFilter::compile(&[], &[], DefaultInclude::All).unwrap();
+        assert!(filter.is_match("Assets/Models/cube.fbx"));
+    }
+
+    #[test]
+    fn default_none_matches_nothing_without_includes() {
+        let filter = AssetFilter::compile(&[], &[], DefaultInclude::None).unwrap();
+        assert!(!filter.is_match("Assets/Models/cube.fbx"));
+    }
+
+    #[test]
+    fn include_pattern_overrides_default_none() {
+        let filter = AssetFilter::compile(&["Assets/Models/**".to_string()], &[], DefaultInclude::None).unwrap();
+        assert!(filter.is_match("Assets/Models/cube.fbx"));
+        assert!(!filter.is_match("Assets/Textures/cube.png"));
+    }
+
+    #[test]
+    fn exclude_takes_precedence_over_include() {
+        let filter = AssetFilter::compile(
+            &["Assets/**".to_string()],
+            &["Assets/Models/**".to_string()],
+            DefaultInclude::All,
+        )
+        .unwrap();
+        assert!(!filter.is_match("Assets/Models/cube.fbx"));
+        assert!(filter.is_match("Assets/Textures/cube.png"));
+    }
+}
@@ -0,0 +1,39 @@
+use std::path::{Component, Path};
+
+use anyhow::{bail, Result};
+
+/// Rejects any path whose components are not all plain `Normal` components (no `..`, no root,
+/// no prefix/drive). Used for every destination path derived from an archived `pathname`,
+/// whether it is about to be joined onto a filesystem root or written as a zip entry name.
+pub fn validate_relative_path(path: &str) -> Result<&Path> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        bail!("asset path escapes root directory: {path}");
+    }
+
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_relative_paths() {
+        assert!(validate_relative_path("Assets/Models/cube.fbx").is_ok());
+    }
+
+    #[test]
+    fn rejects_parent_traversal() {
+        assert!(validate_relative_path("../../etc/passwd").is_err());
+        assert!(validate_relative_path("Assets/../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        assert!(validate_relative_path("/etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{bail, Context, Result};
+
+/// Checks `count` (already incremented for the asset about to be extracted) against `max_files`.
+pub fn enforce_file_count(count: u64, max_files: u64) -> Result<()> {
+    if count > max_files {
+        bail!("asset count exceeds --max-files ({max_files})");
+    }
+
+    Ok(())
+}
+
+/// Checks a single tar entry's size against `--max-file-size` before it's read into memory.
+pub fn enforce_entry_size(size: u64, max_file_size: u64, entry_path: &str) -> Result<()> {
+    if size > max_file_size {
+        bail!("entry exceeds --max-file-size ({max_file_size} bytes): {entry_path}");
+    }
+
+    Ok(())
+}
+
+/// Adds `amount` onto `running_total`, failing on overflow or once `max_total_size` is exceeded.
+pub fn accumulate_total_size(running_total: u64, amount: u64, max_total_size: u64) -> Result<u64> {
+    let total = running_total
+        .checked_add(amount)
+        .context("total extracted size overflowed")?;
+    if total > max_total_size {
+        bail!("total extracted size exceeds --max-total-size ({max_total_size} bytes)");
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforce_file_count_allows_up_to_the_limit() {
+        assert!(enforce_file_count(5, 5).is_ok());
+    }
+
+    #[test]
+    fn enforce_file_count_rejects_over_the_limit() {
+        assert!(enforce_file_count(6, 5).is_err());
+    }
+
+    #[test]
+    fn enforce_entry_size_allows_up_to_the_limit() {
+        assert!(enforce_entry_size(5, 5, "asset").is_ok());
+    }
+
+    #[test]
+    fn enforce_entry_size_rejects_over_the_limit() {
+        assert!(enforce_entry_size(6, 5, "asset").is_err());
+    }
+
+    #[test]
+    fn accumulate_total_size_allows_up_to_the_limit() {
+        assert_eq!(accumulate_total_size(40, 10, 50).unwrap(), 50);
+    }
+
+    #[test]
+    fn accumulate_total_size_rejects_over_the_limit() {
+        assert!(accumulate_total_size(40, 11, 50).is_err());
+    }
+
+    #[test]
+    fn accumulate_total_size_rejects_overflow() {
+        assert!(accumulate_total_size(u64::MAX, 1, u64::MAX).is_err());
+    }
+}
@@ -1,34 +1,70 @@
 use std::{
-    fs::File,
-    io::{BufReader, Read},
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use anyhow::{bail, Context, Result};
+use async_compression::tokio::bufread::GzipDecoder;
 use clap::Parser;
-use flate2::read::GzDecoder;
-use tar::Archive;
 use tokio::{
-    fs::{create_dir_all, write},
+    fs::{canonicalize, create_dir_all, symlink_metadata, write, File},
+    io::{stdin, AsyncRead, AsyncReadExt, BufReader},
     spawn,
     sync::{OwnedSemaphorePermit, Semaphore},
 };
+use tokio_stream::StreamExt;
+use tokio_tar::Archive;
+
+mod archive;
+mod filter;
+mod guard;
+mod limits;
+mod manifest;
+mod policy;
+
+use crate::{
+    archive::{spawn_zip_writer, ZipEntry},
+    filter::{AssetFilter, DefaultInclude},
+    guard::validate_relative_path,
+    limits::{accumulate_total_size, enforce_entry_size, enforce_file_count},
+    manifest::{Manifest, PreviewLocation, PreviewPayload},
+    policy::{entry_mtime, should_write, ExistingPolicy, OnErrorPolicy},
+};
 
 /// unitypackage extractor
 #[derive(Debug, Clone, Parser)]
 #[clap(about, version, author)]
 pub struct Arguments {
-    /// unitypackage file to extract.
+    /// unitypackage file to extract. Pass `-` to read the archive from stdin.
     pub unity_package: PathBuf,
 
-    /// Target directory to extract to.
+    /// Target directory to extract to. Ignored when `--output-zip` is given.
     pub root_directory: PathBuf,
 
+    /// Write every selected asset into a single zip archive at this path instead of
+    /// materializing loose files under `root_directory`.
+    #[clap(long)]
+    pub output_zip: Option<PathBuf>,
+
     /// Remove path prefix and extract assets only that have specified prefix.
     #[clap(short, long)]
     pub prefix: Option<String>,
 
+    /// Glob pattern matched against the decoded pathname; only matching assets are extracted.
+    /// Can be repeated. Excludes always take precedence over includes.
+    #[clap(long = "include")]
+    pub includes: Vec<String>,
+
+    /// Glob pattern matched against the decoded pathname; matching assets are never extracted.
+    /// Can be repeated. Takes precedence over `--include`.
+    #[clap(long = "exclude")]
+    pub excludes: Vec<String>,
+
+    /// Disposition applied to an asset when no `--include` pattern is given (default: all).
+    #[clap(long, value_enum, default_value = "all")]
+    pub default_include: DefaultInclude,
+
     /// Extracts .meta files.
     #[clap(short, long)]
     pub meta: bool,
@@ -40,134 +76,456 @@ pub struct Arguments {
     /// Extraction concurrency (default: 256).
     #[clap(short = 'c', long, default_value = "256")]
     pub max_concurrency: usize,
+
+    /// Maximum size in bytes allowed for a single extracted asset (default: 1 GiB).
+    #[clap(long, default_value = "1073741824")]
+    pub max_file_size: u64,
+
+    /// Maximum cumulative size in bytes allowed across all extracted assets (default: 16 GiB).
+    #[clap(long, default_value = "17179869184")]
+    pub max_total_size: u64,
+
+    /// Maximum number of assets allowed to be extracted (default: 100000).
+    #[clap(long, default_value = "100000")]
+    pub max_files: u64,
+
+    /// How to react when an individual asset fails to parse, read, or write (default: abort).
+    #[clap(long, value_enum, default_value = "abort")]
+    pub on_error: OnErrorPolicy,
+
+    /// Write a JSON manifest (pathname, size, GUID, content hash) of every asset selected by
+    /// `--prefix` and the include/exclude filters here.
+    #[clap(long)]
+    pub manifest: Option<PathBuf>,
+
+    /// Also extract each asset's preview.png, either alongside the asset or into a
+    /// `previews/` subdirectory keyed by GUID.
+    #[clap(long, value_enum)]
+    pub extract_previews: Option<PreviewLocation>,
+
+    /// How to react when an asset or its .meta file already exists on disk (default: overwrite).
+    #[clap(long, value_enum, default_value = "overwrite")]
+    pub existing: ExistingPolicy,
+
+    /// Only write over an existing file when the archived asset's mtime is newer.
+    #[clap(long)]
+    pub keep_newer: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Arguments::parse();
+    let asset_filter = AssetFilter::compile(&args.includes, &args.excludes, args.default_include)?;
 
     let mut up_archive = {
-        let up_file = File::open(args.unity_package)?;
-        let up_tar_stream = GzDecoder::new(BufReader::new(up_file));
+        let up_source: Box<dyn AsyncRead + Unpin + Send> = if args.unity_package == Path::new("-") {
+            Box::new(stdin())
+        } else {
+            Box::new(
+                File::open(&args.unity_package)
+                    .await
+                    .with_context(|| format!("failed to open {}", args.unity_package.display()))?,
+            )
+        };
+        let up_tar_stream = GzipDecoder::new(BufReader::new(up_source));
         Archive::new(up_tar_stream)
     };
 
-    let mut prefix = String::new();
-    let mut asset_bytes = vec![];
-    let mut asset_meta_bytes = vec![];
-    let mut asset_path = String::new();
-
-    let mut asset_met = true;
-    let mut asset_meta_met = true;
-    let mut asset_path_met = true;
-    // let mut asset_preview_met = true;
+    let mut pending = PendingAsset::default();
 
     let extract_gate = Arc::new(Semaphore::new(args.max_concurrency + 1));
     let parent_permit = extract_gate.clone().acquire_owned().await?;
 
-    for entry in up_archive.entries()? {
+    let mut zip_writer = args
+        .output_zip
+        .clone()
+        .map(|output_path| spawn_zip_writer(output_path, args.on_error));
+    let manifest = args.manifest.is_some().then(Manifest::new);
+    let mut state = ExtractionState::new(manifest);
+
+    let mut entries = up_archive.entries()?;
+    while let Some(entry) = entries.next().await {
         let mut entry = entry?;
         let entry_path = entry.path()?.into_owned();
         let entry_path_str = entry_path.to_string_lossy().to_string();
 
-        // new asset paths start
-        if prefix.is_empty() || !entry_path_str.starts_with(&prefix) {
-            prefix = entry_path_str.to_string();
-            asset_bytes.clear();
-            asset_meta_bytes.clear();
-            asset_path.clear();
-
-            asset_met = false;
-            asset_meta_met = false;
-            asset_path_met = false;
-            // asset_preview_met = false;
-
+        // A new GUID's block starts. Flush whatever was pending first: the trailing entry of a
+        // block (commonly `preview.png`, which isn't guaranteed to come before `pathname`) is
+        // only known to be missing once we see the next block start.
+        if pending.prefix.is_empty() || !entry_path_str.starts_with(&pending.prefix) {
+            let completed = std::mem::replace(&mut pending, PendingAsset::started(entry_path_str.clone()));
+            if completed.is_complete() {
+                let zip_tx = zip_writer.as_ref().map(|(tx, _)| tx);
+                finish_pending_asset(completed, &args, &asset_filter, zip_tx, &extract_gate, &mut state).await?;
+            }
             continue;
         }
 
-        let Some(filename) = entry_path_str.strip_prefix(&prefix) else {
-            bail!("invalid package filename detected: {entry_path_str}");
-        };
-        match filename {
-            "asset" => {
-                asset_met = true;
-                entry.read_to_end(&mut asset_bytes)?;
-            }
-            "asset.meta" => {
-                asset_meta_met = true;
-                entry.read_to_end(&mut asset_meta_bytes)?;
-            }
-            "pathname" => {
-                asset_path_met = true;
-                entry.read_to_string(&mut asset_path)?;
+        let asset_result: Result<()> = async {
+            let filename = entry_path_str
+                .strip_prefix(&pending.prefix)
+                .with_context(|| format!("invalid package filename detected: {entry_path_str}"))?;
+            match filename {
+                "asset" => {
+                    enforce_entry_size(entry.header().size()?, args.max_file_size, &entry_path_str)?;
+                    pending.asset_mtime = entry.header().mtime().ok().map(entry_mtime);
+                    entry.read_to_end(&mut pending.asset_bytes).await?;
+                    pending.asset_met = true;
+                }
+                "asset.meta" => {
+                    enforce_entry_size(entry.header().size()?, args.max_file_size, &entry_path_str)?;
+                    entry.read_to_end(&mut pending.asset_meta_bytes).await?;
+                    pending.asset_meta_met = true;
+                }
+                "pathname" => {
+                    enforce_entry_size(entry.header().size()?, args.max_file_size, &entry_path_str)?;
+                    entry.read_to_string(&mut pending.asset_path).await?;
+                    pending.asset_path_met = true;
+                }
+                "preview.png" => {
+                    if args.extract_previews.is_some() {
+                        enforce_entry_size(entry.header().size()?, args.max_file_size, &entry_path_str)?;
+                        entry.read_to_end(&mut pending.asset_preview_bytes).await?;
+                    }
+                }
+                _ => bail!("unknown file contained: {filename}"),
             }
-            "preview.png" => {
-                // asset_preview_met = true;
-            }
-            _ => bail!("unknown file contained: {filename}"),
+            Ok(())
         }
+        .await;
 
-        // asset data has all met
-        if asset_met && asset_meta_met && asset_path_met {
-            let asset_path = if let Some(extract_prefix) = args.prefix.as_deref() {
-                let Ok(stripped) = Path::new(&asset_path).strip_prefix(extract_prefix) else {
-                    continue;
-                };
-                stripped.to_string_lossy().to_string()
-            } else {
-                asset_path.clone()
-            };
-
-            println!("Extracting \"{asset_path}\" ({} bytes)", asset_bytes.len());
-            let permit = extract_gate.clone().acquire_owned().await?;
-
-            if args.dry {
-                spawn(async {
-                    drop(permit);
-                });
-            } else {
-                spawn(extract_task(
-                    permit,
-                    args.root_directory.clone(),
-                    asset_path,
-                    asset_bytes,
-                    args.meta.then_some(asset_meta_bytes),
-                ));
+        if let Err(error) = asset_result {
+            if !args.on_error.handle(&entry_path_str, &error) {
+                return Err(error);
             }
-
-            prefix.clear();
-            asset_bytes = vec![];
-            asset_meta_bytes = vec![];
+            // Leave `pending` intact: clearing it here would make the "new asset" check above
+            // misread this GUID's remaining sibling entries (e.g. a still-unread
+            // `asset.meta`/`pathname`) as their own bogus single-entry groups, discarding
+            // otherwise-valid data along with the one malformed entry.
+            continue;
         }
     }
 
+    // Flush whatever was still pending when the archive ended.
+    if pending.is_complete() {
+        let zip_tx = zip_writer.as_ref().map(|(tx, _)| tx);
+        finish_pending_asset(pending, &args, &asset_filter, zip_tx, &extract_gate, &mut state).await?;
+    }
+
     // wait all file extraction
     drop(parent_permit);
     let _ = extract_gate
         .acquire_many_owned((args.max_concurrency + 1) as u32)
         .await?;
 
+    for (asset_path, handle) in state.task_handles {
+        let result = handle.await.context("extraction task panicked")?;
+        if let Err(error) = result {
+            if !args.on_error.handle(&asset_path, &error) {
+                return Err(error);
+            }
+            state.skipped_assets += 1;
+        }
+    }
+
+    if let Some((zip_tx, zip_handle)) = zip_writer.take() {
+        drop(zip_tx);
+        zip_handle.await.context("zip writer task panicked")??;
+    }
+
+    if let Some(manifest) = state.manifest.as_ref() {
+        let manifest_path = args.manifest.as_deref().context("--manifest path missing")?;
+        manifest.write_to(manifest_path).await?;
+    }
+
+    if state.skipped_assets > 0 {
+        eprintln!(
+            "Skipped {} asset(s) due to errors (--on-error={:?})",
+            state.skipped_assets, args.on_error
+        );
+    }
+
+    Ok(())
+}
+
+/// Accumulates the `asset`/`asset.meta`/`pathname`/`preview.png` tar entries that make up one
+/// GUID's block. Tar doesn't guarantee what order sibling entries appear in, so completion is
+/// only known once the next block starts (or the archive ends) — see `PendingAsset::is_complete`.
+#[derive(Default)]
+struct PendingAsset {
+    prefix: String,
+    asset_bytes: Vec<u8>,
+    asset_meta_bytes: Vec<u8>,
+    asset_path: String,
+    asset_preview_bytes: Vec<u8>,
+    asset_mtime: Option<SystemTime>,
+    asset_met: bool,
+    asset_meta_met: bool,
+    asset_path_met: bool,
+}
+
+impl PendingAsset {
+    fn started(prefix: String) -> PendingAsset {
+        PendingAsset {
+            prefix,
+            ..PendingAsset::default()
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.asset_met && self.asset_meta_met && self.asset_path_met
+    }
+}
+
+/// Mutable bookkeeping threaded through every finalized asset: resource counters, the pending
+/// extraction-task handles, and the optional manifest being built up.
+struct ExtractionState {
+    extracted_files: u64,
+    extracted_total_bytes: u64,
+    skipped_assets: u64,
+    task_handles: Vec<(String, tokio::task::JoinHandle<Result<()>>)>,
+    manifest: Option<Manifest>,
+}
+
+impl ExtractionState {
+    fn new(manifest: Option<Manifest>) -> ExtractionState {
+        ExtractionState {
+            extracted_files: 0,
+            extracted_total_bytes: 0,
+            skipped_assets: 0,
+            task_handles: vec![],
+            manifest,
+        }
+    }
+}
+
+/// Finalizes one completed GUID block: applies prefix-stripping and the asset filter, checks
+/// resource caps, records it in the manifest, and either queues it for the output zip or spawns
+/// an extraction task for it. Resource-cap failures abort the whole run independent of
+/// `--on-error`, since caps are a run-wide guarantee rather than a per-asset recoverable error.
+async fn finish_pending_asset(
+    pending: PendingAsset,
+    args: &Arguments,
+    asset_filter: &AssetFilter,
+    zip_tx: Option<&tokio::sync::mpsc::UnboundedSender<ZipEntry>>,
+    extract_gate: &Arc<Semaphore>,
+    state: &mut ExtractionState,
+) -> Result<()> {
+    let PendingAsset {
+        prefix,
+        asset_bytes,
+        asset_meta_bytes,
+        asset_path,
+        mut asset_preview_bytes,
+        asset_mtime,
+        ..
+    } = pending;
+
+    let guid = prefix.trim_end_matches('/').to_string();
+    let asset_path = if let Some(extract_prefix) = args.prefix.as_deref() {
+        let Ok(stripped) = Path::new(&asset_path).strip_prefix(extract_prefix) else {
+            return Ok(());
+        };
+        stripped.to_string_lossy().to_string()
+    } else {
+        asset_path
+    };
+
+    if !asset_filter.is_match(&asset_path) {
+        return Ok(());
+    }
+
+    if let Some(manifest) = state.manifest.as_mut() {
+        manifest.record(guid.clone(), asset_path.clone(), &asset_bytes);
+    }
+
+    let preview = args
+        .extract_previews
+        .filter(|_| !asset_preview_bytes.is_empty())
+        .map(|location| PreviewPayload {
+            location,
+            guid,
+            bytes: std::mem::take(&mut asset_preview_bytes),
+        });
+
+    state.extracted_files += 1;
+    enforce_file_count(state.extracted_files, args.max_files)?;
+    state.extracted_total_bytes =
+        accumulate_total_size(state.extracted_total_bytes, asset_bytes.len() as u64, args.max_total_size)?;
+
+    println!("Extracting \"{asset_path}\" ({} bytes)", asset_bytes.len());
+    let permit = extract_gate.clone().acquire_owned().await?;
+
+    if args.dry {
+        spawn(async {
+            drop(permit);
+        });
+    } else if let Some(zip_tx) = zip_tx {
+        zip_tx
+            .send(ZipEntry {
+                asset_path,
+                asset_bytes,
+                asset_meta_bytes: args.meta.then_some(asset_meta_bytes),
+                preview,
+            })
+            .ok();
+        drop(permit);
+    } else {
+        let handle = spawn(extract_task(
+            permit,
+            ExtractionRequest {
+                base_path: args.root_directory.clone(),
+                asset_path: asset_path.clone(),
+                asset_bytes,
+                asset_meta_bytes: args.meta.then_some(asset_meta_bytes),
+                preview,
+                asset_mtime,
+                existing: args.existing,
+                keep_newer: args.keep_newer,
+            },
+        ));
+        state.task_handles.push((asset_path, handle));
+    }
+
     Ok(())
 }
 
-async fn extract_task(
-    permit: OwnedSemaphorePermit,
+/// Bundles what `extract_task` needs to write out one completed asset, since its parameter list
+/// had grown past what reads comfortably (and past clippy's `too_many_arguments` threshold)
+/// across the requests that added previews, mtimes, and the existing-file policies.
+struct ExtractionRequest {
     base_path: PathBuf,
     asset_path: String,
     asset_bytes: Vec<u8>,
     asset_meta_bytes: Option<Vec<u8>>,
-) -> Result<()> {
-    let asset_fullpath = base_path.join(&asset_path);
-    let asset_dir = asset_fullpath.parent().context("invalid root path")?;
+    preview: Option<PreviewPayload>,
+    asset_mtime: Option<SystemTime>,
+    existing: ExistingPolicy,
+    keep_newer: bool,
+}
 
-    create_dir_all(asset_dir).await?;
-    write(asset_fullpath, &asset_bytes).await?;
+async fn extract_task(permit: OwnedSemaphorePermit, request: ExtractionRequest) -> Result<()> {
+    let ExtractionRequest {
+        base_path,
+        asset_path,
+        asset_bytes,
+        asset_meta_bytes,
+        preview,
+        asset_mtime,
+        existing,
+        keep_newer,
+    } = request;
+
+    let asset_fullpath = secure_output_path(&base_path, &asset_path).await?;
+    if should_write(existing, keep_newer, &asset_fullpath, asset_mtime).await? {
+        write(&asset_fullpath, &asset_bytes).await?;
+    }
 
     if let Some(meta_bytes) = asset_meta_bytes {
-        let meta_path = base_path.join(format!("{asset_path}.meta"));
-        write(meta_path, &meta_bytes).await?;
+        let meta_path = secure_output_path(&base_path, &format!("{asset_path}.meta")).await?;
+        if should_write(existing, keep_newer, &meta_path, asset_mtime).await? {
+            write(&meta_path, &meta_bytes).await?;
+        }
+    }
+
+    if let Some(preview) = preview {
+        let preview_path = secure_output_path(&base_path, &preview.relative_path(&asset_path)).await?;
+        if should_write(existing, keep_newer, &preview_path, asset_mtime).await? {
+            write(&preview_path, &preview.bytes).await?;
+        }
     }
 
     drop(permit);
     Ok(())
 }
+
+/// Resolves `asset_path` onto `root_directory`, validating its components and verifying
+/// containment under `root_directory` one directory level at a time: each level is created,
+/// canonicalized and checked before anything is created inside it. This closes the gap a symlinked directory component would otherwise open: checking
+/// containment only after `write` completes can't stop bytes from landing outside the root in
+/// the first place, since `create_dir_all`/`write` silently follow symlinks.
+async fn secure_output_path(root_directory: &Path, asset_path: &str) -> Result<PathBuf> {
+    let relative = validate_relative_path(asset_path)?;
+    let file_name = relative.file_name().context("asset path has no file name")?;
+    let relative_dir = relative.parent().unwrap_or_else(|| Path::new(""));
+
+    create_dir_all(root_directory)
+        .await
+        .with_context(|| format!("failed to create root directory {}", root_directory.display()))?;
+    let canonical_root = canonicalize(root_directory)
+        .await
+        .context("failed to canonicalize root directory")?;
+
+    let mut verified_dir = canonical_root.clone();
+    for component in relative_dir.components() {
+        verified_dir.push(component);
+        create_dir_all(&verified_dir)
+            .await
+            .with_context(|| format!("failed to create directory {}", verified_dir.display()))?;
+        verified_dir = canonicalize(&verified_dir)
+            .await
+            .with_context(|| format!("failed to canonicalize directory {}", verified_dir.display()))?;
+        if !verified_dir.starts_with(&canonical_root) {
+            bail!("asset path escapes root directory: {asset_path}");
+        }
+    }
+
+    let full_path = verified_dir.join(file_name);
+    if let Ok(leaf_metadata) = symlink_metadata(&full_path).await {
+        if leaf_metadata.is_symlink() {
+            bail!("refusing to write through existing symlink: {}", full_path.display());
+        }
+    }
+
+    Ok(full_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn secure_output_path_accepts_nested_directories() {
+        let root = tempfile::tempdir().unwrap();
+
+        let resolved = secure_output_path(root.path(), "Assets/Models/cube.fbx")
+            .await
+            .unwrap();
+
+        assert!(resolved.starts_with(canonicalize(root.path()).await.unwrap()));
+        assert!(resolved.ends_with("Assets/Models/cube.fbx"));
+        assert!(root.path().join("Assets/Models").is_dir());
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn secure_output_path_rejects_symlinked_directory_escape() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+
+        // A directory component under the root that is actually a symlink pointing outside it.
+        symlink(outside.path(), root.path().join("Assets")).unwrap();
+
+        let result = secure_output_path(root.path(), "Assets/cube.fbx").await;
+
+        assert!(result.is_err());
+        assert!(!outside.path().join("cube.fbx").exists());
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn secure_output_path_rejects_existing_leaf_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+
+        symlink(outside.path().join("cube.fbx"), root.path().join("cube.fbx")).unwrap();
+
+        let result = secure_output_path(root.path(), "cube.fbx").await;
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::fs::write;
+
+/// Where to place an asset's extracted `preview.png`, when `--extract-previews` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PreviewLocation {
+    /// Write `<asset_path>.preview.png` next to the extracted asset.
+    Alongside,
+    /// Write `previews/<guid>.png` under the root directory.
+    Subdirectory,
+}
+
+/// A decoded `preview.png` queued for extraction alongside its asset.
+#[derive(Debug, Clone)]
+pub struct PreviewPayload {
+    pub location: PreviewLocation,
+    pub guid: String,
+    pub bytes: Vec<u8>,
+}
+
+impl PreviewPayload {
+    /// Path (relative to the root directory / zip archive) the preview should be written to.
+    pub fn relative_path(&self, asset_path: &str) -> String {
+        match self.location {
+            PreviewLocation::Alongside => format!("{asset_path}.preview.png"),
+            PreviewLocation::Subdirectory => format!("previews/{}.png", self.guid),
+        }
+    }
+}
+
+/// A single scanned asset's metadata, as recorded in the `--manifest` JSON output.
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub guid: String,
+    pub pathname: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Accumulates `ManifestEntry` records while scanning and writes them out as JSON.
+#[derive(Debug, Default)]
+pub struct Manifest {
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new() -> Manifest {
+        Manifest::default()
+    }
+
+    /// Records one scanned asset, hashing `asset_bytes` with SHA-256 for diffing/dedup.
+    pub fn record(&mut self, guid: String, pathname: String, asset_bytes: &[u8]) {
+        let hash = Sha256::digest(asset_bytes);
+        self.entries.push(ManifestEntry {
+            guid,
+            pathname,
+            size: asset_bytes.len() as u64,
+            hash: hex::encode(hash),
+        });
+    }
+
+    pub async fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(&self.entries).context("failed to serialize manifest")?;
+        write(path, json)
+            .await
+            .with_context(|| format!("failed to write manifest to {}", path.display()))
+    }
+}
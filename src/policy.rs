@@ -0,0 +1,129 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use tokio::fs::metadata;
+
+/// How to react when extracting an individual asset fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnErrorPolicy {
+    /// Abort the whole run on the first error.
+    Abort,
+    /// Drop the failing asset and continue, without printing the individual error.
+    Skip,
+    /// Drop the failing asset and continue, printing the error to stderr.
+    Log,
+}
+
+impl OnErrorPolicy {
+    /// Reports `error` according to this policy and returns whether the run should continue.
+    pub fn handle(self, context: &str, error: &anyhow::Error) -> bool {
+        match self {
+            OnErrorPolicy::Abort => false,
+            OnErrorPolicy::Skip => true,
+            OnErrorPolicy::Log => {
+                eprintln!("warning: {context}: {error:#}");
+                true
+            }
+        }
+    }
+}
+
+/// How to react when a target path already exists on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExistingPolicy {
+    /// Overwrite whatever is already at the target path.
+    Overwrite,
+    /// Leave the existing file untouched.
+    Skip,
+    /// Fail instead of touching the existing file.
+    Error,
+}
+
+/// Converts a tar entry's mtime (seconds since epoch) into a `SystemTime`.
+pub fn entry_mtime(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+/// Decides whether `target_path` should be (over)written, combining `--existing` and
+/// `--keep-newer`. `--existing=error` always takes precedence: it fails on any existing target
+/// rather than letting `--keep-newer` silently skip a stale-but-existing file. For the
+/// `Overwrite`/`Skip` policies, `--keep-newer` refines the decision whenever both mtimes are
+/// available.
+pub async fn should_write(
+    existing: ExistingPolicy,
+    keep_newer: bool,
+    target_path: &Path,
+    incoming_mtime: Option<SystemTime>,
+) -> Result<bool> {
+    let Ok(existing_metadata) = metadata(target_path).await else {
+        return Ok(true);
+    };
+
+    if existing == ExistingPolicy::Error {
+        bail!("target already exists: {}", target_path.display());
+    }
+
+    if keep_newer {
+        if let (Some(incoming), Ok(existing_mtime)) = (incoming_mtime, existing_metadata.modified()) {
+            return Ok(incoming > existing_mtime);
+        }
+    }
+
+    match existing {
+        ExistingPolicy::Overwrite => Ok(true),
+        ExistingPolicy::Skip => Ok(false),
+        ExistingPolicy::Error => unreachable!("handled above"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn existing_file() -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cube.fbx");
+        std::fs::write(&path, b"existing").unwrap();
+        (dir, path)
+    }
+
+    #[tokio::test]
+    async fn writes_when_target_is_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("cube.fbx");
+
+        assert!(should_write(ExistingPolicy::Skip, false, &missing, None).await.unwrap());
+        assert!(should_write(ExistingPolicy::Error, false, &missing, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn error_policy_fails_even_when_keep_newer_would_skip() {
+        let (_dir, path) = existing_file();
+        let incoming = SystemTime::now() - Duration::from_secs(60);
+
+        assert!(should_write(ExistingPolicy::Error, true, &path, Some(incoming)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn skip_policy_never_overwrites_without_keep_newer() {
+        let (_dir, path) = existing_file();
+
+        assert!(!should_write(ExistingPolicy::Skip, false, &path, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn keep_newer_overwrites_only_when_incoming_is_newer() {
+        let (_dir, path) = existing_file();
+        let older = SystemTime::now() - Duration::from_secs(60);
+        let newer = SystemTime::now() + Duration::from_secs(60);
+
+        assert!(!should_write(ExistingPolicy::Overwrite, true, &path, Some(older)).await.unwrap());
+        assert!(should_write(ExistingPolicy::Overwrite, true, &path, Some(newer)).await.unwrap());
+    }
+}